@@ -2,7 +2,11 @@
 
 use faiss_sys::*;
 use error::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::{c_int, c_void};
 use std::ptr;
+use std::rc::Rc;
 
 /// Common interface for GPU resources used by Faiss.
 pub trait GpuResources {
@@ -24,6 +28,26 @@ pub trait GpuResources {
     /// Set amount of pinned memory to allocate, for async GPU <-> CPU
     /// transfers
     fn set_pinned_memory(&mut self, size: usize) -> Result<()>;
+
+    /// Enqueue faiss's GPU kernels for `device` onto `stream` (a raw
+    /// `cudaStream_t`) instead of the stream faiss creates by default,
+    /// so that faiss's work can be interleaved with the caller's own
+    /// CUDA pipeline on that device.
+    ///
+    /// Defaults to a no-op, so existing implementors of this trait
+    /// keep compiling without changes; override it to actually enqueue
+    /// work on the given stream.
+    fn set_default_stream(&mut self, _device: i32, _stream: *mut c_void) -> Result<()> {
+        Ok(())
+    }
+
+    /// Force every device to use the default/null CUDA stream, undoing
+    /// any per-device streams set with [`set_default_stream`](GpuResources::set_default_stream).
+    ///
+    /// Defaults to a no-op; see [`set_default_stream`](GpuResources::set_default_stream).
+    fn set_default_null_stream_all_devices(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Standard GPU resources descriptor.
@@ -134,6 +158,26 @@ impl GpuResources for StandardGpuResources {
             Ok(())
         }
     }
+
+    fn set_default_stream(&mut self, device: i32, stream: *mut c_void) -> Result<()> {
+        unsafe {
+            faiss_try!(faiss_StandardGpuResources_setDefaultStream(
+                self.inner,
+                device as c_int,
+                stream
+            ));
+            Ok(())
+        }
+    }
+
+    fn set_default_null_stream_all_devices(&mut self) -> Result<()> {
+        unsafe {
+            faiss_try!(faiss_StandardGpuResources_setDefaultNullStreamAllDevices(
+                self.inner
+            ));
+            Ok(())
+        }
+    }
 }
 
 
@@ -157,14 +201,541 @@ impl<'g> GpuResources for &'g mut StandardGpuResources {
     fn set_pinned_memory(&mut self, size: usize) -> Result<()> {
         (**self).set_pinned_memory(size)
     }
+
+    fn set_default_stream(&mut self, device: i32, stream: *mut c_void) -> Result<()> {
+        (**self).set_default_stream(device, stream)
+    }
+
+    fn set_default_null_stream_all_devices(&mut self) -> Result<()> {
+        (**self).set_default_null_stream_all_devices()
+    }
+}
+
+/// Options controlling how a CPU index is transferred to a single GPU.
+///
+/// Plain `into_gpu`/`to_gpu` transfers the index using faiss's
+/// defaults: full (fp32) precision and the id mapping alongside the
+/// vectors on the device. To override any of that, build a
+/// `GpuClonerOptions` and pass it to
+/// [`ToGpuWithOptions::into_gpu_with_options`] instead. For indexes
+/// that are large relative to device memory, trading precision or
+/// host/device placement for headroom is often worth it:
+///
+/// - [`use_float16`](GpuClonerOptions::use_float16) stores vectors and
+///   lookup tables as fp16, roughly halving memory use and speeding up
+///   IVF list scanning at the cost of precision.
+/// - [`use_float16_coarse_quantizer`](GpuClonerOptions::use_float16_coarse_quantizer)
+///   does the same for the coarse quantizer alone.
+/// - [`indices_on_cpu`](GpuClonerOptions::indices_on_cpu) keeps the id
+///   mapping on the host instead of the device, for indexes whose ids
+///   don't fit in device memory alongside the vectors.
+/// - [`reserve_vectors`](GpuClonerOptions::reserve_vectors)
+///   pre-allocates device memory for a given number of vectors before
+///   any are added, avoiding reallocation as the index grows.
+pub struct GpuClonerOptions {
+    inner: *mut FaissGpuClonerOptions,
+}
+
+impl GpuClonerOptions {
+    /// Create a new set of options, matching faiss's defaults.
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let mut ptr = ptr::null_mut();
+            faiss_try!(faiss_GpuClonerOptions_new(&mut ptr));
+            Ok(GpuClonerOptions { inner: ptr })
+        }
+    }
+
+    /// Store vectors and lookup tables as fp16 rather than fp32,
+    /// roughly halving GPU memory use and speeding up IVF scanning.
+    pub fn use_float16(&mut self, use_float16: bool) -> &mut Self {
+        unsafe {
+            faiss_GpuClonerOptions_set_useFloat16(self.inner, use_float16 as i32);
+        }
+        self
+    }
+
+    /// Store the coarse quantizer's centroids as fp16 rather than
+    /// fp32.
+    pub fn use_float16_coarse_quantizer(&mut self, use_float16: bool) -> &mut Self {
+        unsafe {
+            faiss_GpuClonerOptions_set_useFloat16CoarseQuantizer(self.inner, use_float16 as i32);
+        }
+        self
+    }
+
+    /// Keep the id mapping on the host rather than the device. Useful
+    /// for indexes whose ids are too large to fit on the device
+    /// alongside the vectors.
+    pub fn indices_on_cpu(&mut self, indices_on_cpu: bool) -> &mut Self {
+        // FaissIndicesOptions: INDICES_CPU = 0, INDICES_IVF = 1,
+        // INDICES_32_BIT = 2, INDICES_64_BIT = 3 (faiss's GPU default).
+        unsafe {
+            faiss_GpuClonerOptions_set_indicesOptions(
+                self.inner,
+                if indices_on_cpu { 0 } else { 3 },
+            );
+        }
+        self
+    }
+
+    /// Pre-allocate device memory for this many vectors before any are
+    /// added, instead of growing the allocation as vectors are added.
+    pub fn reserve_vectors(&mut self, n: i32) -> &mut Self {
+        unsafe {
+            faiss_GpuClonerOptions_set_reserveVecs(self.inner, n);
+        }
+        self
+    }
+}
+
+impl Drop for GpuClonerOptions {
+    fn drop(&mut self) {
+        unsafe {
+            faiss_GpuClonerOptions_free(self.inner);
+        }
+    }
+}
+
+/// Low-level helper backing `into_gpu`/`to_gpu` implementations: calls
+/// `faiss_index_cpu_to_gpu_with_options` directly on a raw CPU index
+/// pointer, returning the raw GPU index pointer it produces.
+///
+/// Index wrapper types call this, supplying their own inner pointer
+/// and wrapping the result in their own GPU index type, the same way
+/// [`index_cpu_to_gpu_multiple_ptr`] backs the multi-device path.
+pub fn index_cpu_to_gpu_ptr<G>(
+    resources: &G,
+    device: i32,
+    index: *mut FaissIndex,
+    options: &GpuClonerOptions,
+) -> Result<*mut FaissIndex>
+where
+    G: GpuResources,
+{
+    unsafe {
+        let mut out = ptr::null_mut();
+        faiss_try!(faiss_index_cpu_to_gpu_with_options(
+            resources.inner_ptr(),
+            device as c_int,
+            index,
+            options.inner,
+            &mut out
+        ));
+        Ok(out)
+    }
+}
+
+/// Extends index types that can be moved to a single GPU with the
+/// ability to control the transfer via a [`GpuClonerOptions`], rather
+/// than faiss's defaults.
+pub trait ToGpuWithOptions {
+    /// The resulting GPU index type.
+    type Output;
+
+    /// Move this index to `device` using `resources`, applying
+    /// `options` to the transfer (e.g. fp16 storage, reserved device
+    /// memory, id placement).
+    fn into_gpu_with_options<G>(
+        self,
+        resources: &G,
+        device: i32,
+        options: &GpuClonerOptions,
+    ) -> Result<Self::Output>
+    where
+        G: GpuResources;
+}
+
+impl ToGpuWithOptions for *mut FaissIndex {
+    type Output = *mut FaissIndex;
+
+    /// Move the native index `self` points to, delegating to
+    /// [`index_cpu_to_gpu_ptr`]. Index wrapper types that expose their
+    /// inner `*mut FaissIndex` can forward their own
+    /// `into_gpu_with_options` to this impl and wrap the resulting
+    /// pointer in their own GPU index type.
+    fn into_gpu_with_options<G>(
+        self,
+        resources: &G,
+        device: i32,
+        options: &GpuClonerOptions,
+    ) -> Result<Self::Output>
+    where
+        G: GpuResources,
+    {
+        index_cpu_to_gpu_ptr(resources, device, self, options)
+    }
+}
+
+/// How an index's data is distributed across devices by
+/// [`ToGpuMultiple::into_gpu_multiple`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuMultipleMode {
+    /// Replicate the whole index onto every device. A search is run
+    /// against every replica and the identical top-k results are
+    /// returned; this trades memory (the full index is kept on each
+    /// device) for query throughput.
+    Replicated,
+    /// Shard the index's vectors across devices, so the aggregate
+    /// index size scales with the number of devices rather than being
+    /// bounded by a single device's memory. A search is scattered to
+    /// every shard and the per-shard top-k results are merged.
+    Sharded,
+}
+
+/// Options controlling how an index is distributed across multiple
+/// GPU devices by [`ToGpuMultiple::into_gpu_multiple`].
+pub struct GpuMultipleClonerOptions {
+    inner: *mut FaissGpuMultipleClonerOptions,
+}
+
+impl GpuMultipleClonerOptions {
+    /// Create a new set of options, defaulting to
+    /// [`GpuMultipleMode::Sharded`].
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let mut ptr = ptr::null_mut();
+            faiss_try!(faiss_GpuMultipleClonerOptions_new(&mut ptr));
+            let mut options = GpuMultipleClonerOptions { inner: ptr };
+            options.set_mode(GpuMultipleMode::Sharded);
+            Ok(options)
+        }
+    }
+
+    /// Select whether the index is replicated or sharded across the
+    /// target devices.
+    pub fn set_mode(&mut self, mode: GpuMultipleMode) -> &mut Self {
+        unsafe {
+            let shard = match mode {
+                GpuMultipleMode::Replicated => 0,
+                GpuMultipleMode::Sharded => 1,
+            };
+            faiss_GpuMultipleClonerOptions_set_shard(self.inner, shard);
+        }
+        self
+    }
+}
+
+impl Drop for GpuMultipleClonerOptions {
+    fn drop(&mut self) {
+        unsafe {
+            faiss_GpuMultipleClonerOptions_free(self.inner);
+        }
+    }
+}
+
+/// Extends index types that can be moved to a single GPU (see
+/// `into_gpu` on the index traits) with the ability to distribute
+/// themselves across several devices at once.
+///
+/// This is the main scalability path for the GPU module: rather than
+/// pinning an index to one device, an index can be replicated onto
+/// every device for higher query throughput, or sharded across them so
+/// that aggregate memory scales with the number of devices. Searches
+/// issued against the resulting index are scattered to every
+/// underlying device and the top-k results gathered back into a single
+/// response, transparently to the caller.
+pub trait ToGpuMultiple {
+    /// The resulting multi-device GPU index type.
+    type Output;
+
+    /// Distribute this index across `devices`, one [`GpuResources`] per
+    /// device, according to `options`.
+    fn into_gpu_multiple<G>(
+        self,
+        resources: &[G],
+        devices: &[i32],
+        options: &GpuMultipleClonerOptions,
+    ) -> Result<Self::Output>
+    where
+        G: GpuResources;
+}
+
+/// Low-level helper backing [`ToGpuMultiple`] implementations: calls
+/// `faiss_index_cpu_to_gpu_multiple_with_options` directly on a raw CPU
+/// index pointer, returning the raw multi-device GPU index pointer it
+/// produces.
+///
+/// Index wrapper types implement [`ToGpuMultiple`] in terms of this
+/// function, supplying their own inner pointer and wrapping the result
+/// in their own GPU index type.
+pub fn index_cpu_to_gpu_multiple_ptr<G>(
+    resources: &[G],
+    devices: &[i32],
+    index: *mut FaissIndex,
+    options: &GpuMultipleClonerOptions,
+) -> Result<*mut FaissIndex>
+where
+    G: GpuResources,
+{
+    assert_eq!(
+        resources.len(),
+        devices.len(),
+        "one set of GpuResources must be given per device"
+    );
+    unsafe {
+        let mut providers: Vec<*mut FaissGpuResources> =
+            resources.iter().map(|r| r.inner_ptr()).collect();
+        let mut devices: Vec<c_int> = devices.iter().map(|&d| d as c_int).collect();
+        let mut out = ptr::null_mut();
+        faiss_try!(faiss_index_cpu_to_gpu_multiple_with_options(
+            providers.as_mut_ptr(),
+            providers.len(),
+            devices.as_mut_ptr(),
+            devices.len(),
+            index,
+            options.inner,
+            &mut out
+        ));
+        Ok(out)
+    }
+}
+
+impl ToGpuMultiple for *mut FaissIndex {
+    type Output = *mut FaissIndex;
+
+    /// Distribute the native index `self` points to across `devices`,
+    /// delegating to [`index_cpu_to_gpu_multiple_ptr`]. Index wrapper
+    /// types that expose their inner `*mut FaissIndex` can forward
+    /// their own `into_gpu_multiple` to this impl and wrap the
+    /// resulting pointer in their own GPU index type.
+    fn into_gpu_multiple<G>(
+        self,
+        resources: &[G],
+        devices: &[i32],
+        options: &GpuMultipleClonerOptions,
+    ) -> Result<Self::Output>
+    where
+        G: GpuResources,
+    {
+        index_cpu_to_gpu_multiple_ptr(resources, devices, self, options)
+    }
+}
+
+/// Configuration applied uniformly to every device a
+/// [`GpuResourcesManager`] hands out resources for.
+///
+/// These mirror the per-device setters on [`GpuResources`], but are
+/// applied once, at the point a device's resources are first created,
+/// rather than by the caller on each individual handle.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GpuResourcesManagerOptions {
+    /// Fixed amount of temporary memory to reserve on each device, in
+    /// bytes. See [`GpuResources::set_temp_memory`].
+    pub temp_memory: Option<usize>,
+    /// Amount of pinned host memory to reserve for async transfers, in
+    /// bytes. See [`GpuResources::set_pinned_memory`].
+    pub pinned_memory: Option<usize>,
+}
+
+/// A handle to the GPU resources pooled for a single device by a
+/// [`GpuResourcesManager`].
+///
+/// This is a cheap, reference-counted clone of the manager's entry for
+/// that device: any number of indexes may hold one, and the underlying
+/// `StandardGpuResources` is only torn down once the manager and every
+/// handle derived from it have been dropped. This ordering is what
+/// prevents the native resources object from being freed while an
+/// index built from it is still alive.
+pub struct ManagedGpuResources {
+    device: i32,
+    inner: Rc<RefCell<StandardGpuResources>>,
+}
+
+impl ManagedGpuResources {
+    /// The CUDA device ordinal these resources were configured for.
+    pub fn device(&self) -> i32 {
+        self.device
+    }
+}
+
+impl Clone for ManagedGpuResources {
+    fn clone(&self) -> Self {
+        ManagedGpuResources {
+            device: self.device,
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl GpuResources for ManagedGpuResources {
+    fn inner_ptr(&self) -> *mut FaissGpuResources {
+        self.inner.borrow().inner_ptr()
+    }
+
+    fn no_temp_memory(&mut self) -> Result<()> {
+        self.inner.borrow_mut().no_temp_memory()
+    }
+
+    fn set_temp_memory(&mut self, size: usize) -> Result<()> {
+        self.inner.borrow_mut().set_temp_memory(size)
+    }
+
+    fn set_temp_memory_fraction(&mut self, fraction: f32) -> Result<()> {
+        self.inner.borrow_mut().set_temp_memory_fraction(fraction)
+    }
+
+    fn set_pinned_memory(&mut self, size: usize) -> Result<()> {
+        self.inner.borrow_mut().set_pinned_memory(size)
+    }
+
+    fn set_default_stream(&mut self, device: i32, stream: *mut c_void) -> Result<()> {
+        self.inner.borrow_mut().set_default_stream(device, stream)
+    }
+
+    fn set_default_null_stream_all_devices(&mut self) -> Result<()> {
+        self.inner.borrow_mut().set_default_null_stream_all_devices()
+    }
+}
+
+/// Pools one [`StandardGpuResources`] per CUDA device, modeled on
+/// Milvus's `FaissGpuResourceMgr`.
+///
+/// Wiring up `StandardGpuResources` by hand for every device and every
+/// index is easy to get wrong: two indexes on the same device end up
+/// with differently configured resources, or a resources object is
+/// dropped while an index built from it is still around. A
+/// `GpuResourcesManager` registers the desired temp-memory and
+/// pinned-memory budget once, then hands out a [`ManagedGpuResources`]
+/// for a given device on request, creating and configuring the
+/// underlying `StandardGpuResources` lazily on first use and reusing it
+/// for every later request for that same device.
+///
+/// # Examples
+///
+/// ```
+/// # fn run() -> Result<(), Box<::std::error::Error>> {
+/// use faiss::gpu::{GpuResourcesManager, GpuResourcesManagerOptions};
+/// use faiss::{MetricType};
+/// use faiss::index::flat::FlatIndex;
+///
+/// let manager = GpuResourcesManager::with_options(GpuResourcesManagerOptions {
+///     temp_memory: Some(256 * 1024 * 1024),
+///     ..Default::default()
+/// });
+/// let resources = manager.resources_for_device(0)?;
+/// let index = FlatIndex::new(64, MetricType::L2)?;
+/// let gpu_index = index.into_gpu(&resources, 0)?;
+/// # Ok(())
+/// # }
+/// # run().unwrap();
+/// ```
+pub struct GpuResourcesManager {
+    options: GpuResourcesManagerOptions,
+    devices: RefCell<HashMap<i32, Rc<RefCell<StandardGpuResources>>>>,
+}
+
+impl GpuResourcesManager {
+    /// Create a new manager using the default resource configuration.
+    pub fn new() -> Self {
+        GpuResourcesManager::with_options(GpuResourcesManagerOptions::default())
+    }
+
+    /// Create a new manager, applying `options` to every device's
+    /// resources at the point they are first created.
+    pub fn with_options(options: GpuResourcesManagerOptions) -> Self {
+        GpuResourcesManager {
+            options,
+            devices: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Obtain the (possibly newly created) resources for the given
+    /// device, configured according to this manager's options.
+    ///
+    /// Calling this repeatedly for the same device returns handles that
+    /// share the same underlying `StandardGpuResources`, so indexes on
+    /// that device contend for the same temporary and pinned memory
+    /// pools rather than each allocating their own.
+    pub fn resources_for_device(&self, device: i32) -> Result<ManagedGpuResources> {
+        let mut devices = self.devices.borrow_mut();
+        if let Some(inner) = devices.get(&device) {
+            return Ok(ManagedGpuResources {
+                device,
+                inner: Rc::clone(inner),
+            });
+        }
+
+        let mut resources = StandardGpuResources::new()?;
+        if let Some(size) = self.options.temp_memory {
+            resources.set_temp_memory(size)?;
+        }
+        if let Some(size) = self.options.pinned_memory {
+            resources.set_pinned_memory(size)?;
+        }
+
+        let inner = Rc::new(RefCell::new(resources));
+        devices.insert(device, Rc::clone(&inner));
+        Ok(ManagedGpuResources { device, inner })
+    }
+}
+
+impl Default for GpuResourcesManager {
+    fn default() -> Self {
+        GpuResourcesManager::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::StandardGpuResources;
+    use super::{GpuResourcesManager, StandardGpuResources};
+    use std::rc::Rc;
 
     #[test]
     fn smoke_detector() {
         StandardGpuResources::new().unwrap();
     }
+
+    #[test]
+    fn manager_reuses_resources_per_device() {
+        let manager = GpuResourcesManager::new();
+        let a = manager.resources_for_device(0).unwrap();
+        let b = manager.resources_for_device(0).unwrap();
+        assert!(Rc::ptr_eq(&a.inner, &b.inner));
+    }
+
+    #[test]
+    #[should_panic(expected = "one set of GpuResources must be given per device")]
+    fn into_gpu_multiple_rejects_mismatched_lengths() {
+        use super::{index_cpu_to_gpu_multiple_ptr, GpuMultipleClonerOptions};
+        use std::ptr;
+
+        let resources: Vec<StandardGpuResources> = Vec::new();
+        let devices = [0, 1];
+        let options = GpuMultipleClonerOptions::new().unwrap();
+        let _ = index_cpu_to_gpu_multiple_ptr(&resources, &devices, ptr::null_mut(), &options);
+    }
+
+    #[test]
+    fn gpu_cloner_options_builder_chains() {
+        use super::GpuClonerOptions;
+
+        let mut options = GpuClonerOptions::new().unwrap();
+        options
+            .use_float16(true)
+            .use_float16_coarse_quantizer(false)
+            .indices_on_cpu(true)
+            .reserve_vectors(1024);
+    }
+
+    #[test]
+    fn into_gpu_with_options_moves_a_flat_index_to_gpu() {
+        use super::{GpuClonerOptions, ToGpuWithOptions};
+        use faiss_sys::{faiss_IndexFlat_new_with, faiss_index_free, FaissMetricType_METRIC_L2};
+        use std::ptr;
+
+        unsafe {
+            let mut index = ptr::null_mut();
+            faiss_IndexFlat_new_with(&mut index, 8, FaissMetricType_METRIC_L2);
+
+            let mut options = GpuClonerOptions::new().unwrap();
+            options.use_float16(true).reserve_vectors(16);
+
+            let resources = StandardGpuResources::new().unwrap();
+            let gpu_index = index.into_gpu_with_options(&resources, 0, &options).unwrap();
+            assert!(!gpu_index.is_null());
+
+            faiss_index_free(index);
+        }
+    }
 }